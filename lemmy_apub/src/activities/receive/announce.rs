@@ -0,0 +1,144 @@
+use crate::{
+  activities::receive::{
+    comment::{receive_create_comment, receive_like_comment},
+    post::{receive_create_post, receive_like_post},
+    verify_activity_domains_valid,
+  },
+  fetcher::{get_or_fetch_and_upsert_comment, get_or_fetch_and_upsert_post},
+};
+use activitystreams::{
+  activity::{Announce, Create, Like},
+  object::{Note, Page},
+  prelude::*,
+};
+use anyhow::Context;
+use lemmy_utils::{location_info, LemmyError};
+use lemmy_websocket::LemmyContext;
+use serde::Deserialize;
+
+/// The activity types that can show up wrapped in an `Announce` when a community relays content
+/// to its followers, but also as plain top-level activities sent straight to a community's inbox.
+/// `community_inbox.rs` dispatches both cases through [`receive_announcable_activity`].
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum AnnouncableActivities {
+  CreateComment(Create<Note>),
+  CreatePost(Create<Page>),
+  Like(Like),
+}
+
+/// Handle an `Announce` activity, ie a community forwarding (boosting) something to its
+/// followers.
+///
+/// The outer activity's ID is verified against the announcing community, with
+/// `object_domain_must_match = false`: the wrapped activity keeps its original author's domain,
+/// which differs from the community's. The wrapped activity is then dispatched to the normal
+/// comment/post/like handlers.
+pub(crate) async fn receive_announce(
+  announce: Announce,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<(), LemmyError> {
+  let community_actor_id = announce
+    .actor()?
+    .as_single_xsd_any_uri()
+    .context(location_info!())?
+    .to_owned();
+  // The inner activity's object legitimately lives on a different domain than the announcing
+  // community, so the embedded object's domain is not checked here.
+  verify_activity_domains_valid(&announce, &community_actor_id, false, request_counter)?;
+
+  let inner = announce.object().to_owned().one().context(location_info!())?;
+  let inner_activity: AnnouncableActivities =
+    serde_json::from_value(serde_json::to_value(inner).context(location_info!())?)
+      .context(location_info!())?;
+
+  receive_announcable_activity(inner_activity, context, request_counter).await
+}
+
+/// Dispatches one of the activity types that can appear either wrapped in an `Announce` or
+/// directly in a community's inbox to the matching comment/post/like handler.
+pub(crate) async fn receive_announcable_activity(
+  activity: AnnouncableActivities,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<(), LemmyError> {
+  match activity {
+    AnnouncableActivities::CreateComment(c) => receive_create_comment(c, context, request_counter).await,
+    AnnouncableActivities::CreatePost(p) => receive_create_post(p, context, request_counter).await,
+    AnnouncableActivities::Like(l) => receive_announced_like(l, context, request_counter).await,
+  }
+}
+
+/// A `Like` wrapped in an `Announce` can target either a post or a comment; try the post first
+/// and fall back to the comment, the same way `flag::receive_flag` resolves its reported object.
+async fn receive_announced_like(
+  like: Like,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<(), LemmyError> {
+  let object_id = like.object().as_single_xsd_any_uri().context(location_info!())?;
+
+  if get_or_fetch_and_upsert_post(object_id, context, request_counter)
+    .await
+    .is_ok()
+  {
+    receive_like_post(like, context, request_counter).await
+  } else {
+    get_or_fetch_and_upsert_comment(object_id, context, request_counter).await?;
+    receive_like_comment(like, context, request_counter).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn announcable_activities_picks_create_comment_for_a_create_note() {
+    let json = serde_json::json!({
+      "type": "Create",
+      "id": "https://example.com/activities/create/1",
+      "actor": "https://example.com/actor",
+      "object": {
+        "type": "Note",
+        "id": "https://example.com/comment/1",
+      },
+    });
+    assert!(matches!(
+      serde_json::from_value::<AnnouncableActivities>(json).unwrap(),
+      AnnouncableActivities::CreateComment(_)
+    ));
+  }
+
+  #[test]
+  fn announcable_activities_picks_create_post_for_a_create_page() {
+    let json = serde_json::json!({
+      "type": "Create",
+      "id": "https://example.com/activities/create/2",
+      "actor": "https://example.com/actor",
+      "object": {
+        "type": "Page",
+        "id": "https://example.com/post/1",
+      },
+    });
+    assert!(matches!(
+      serde_json::from_value::<AnnouncableActivities>(json).unwrap(),
+      AnnouncableActivities::CreatePost(_)
+    ));
+  }
+
+  #[test]
+  fn announcable_activities_picks_like_for_a_like() {
+    let json = serde_json::json!({
+      "type": "Like",
+      "id": "https://example.com/activities/like/1",
+      "actor": "https://example.com/actor",
+      "object": "https://example.com/post/1",
+    });
+    assert!(matches!(
+      serde_json::from_value::<AnnouncableActivities>(json).unwrap(),
+      AnnouncableActivities::Like(_)
+    ));
+  }
+}
@@ -0,0 +1,78 @@
+use actix_web::http::StatusCode;
+use lemmy_utils::LemmyError;
+use thiserror::Error;
+
+/// Distinguishes the ways that handling an inbox activity can fail, so the inbox route can
+/// return a status code that tells the remote server whether retrying is worthwhile.
+#[derive(Debug, Error)]
+pub(crate) enum ReceiveActivityError {
+  #[error("Activity type is not supported")]
+  Unsupported,
+  #[error("Activity domain does not match actor, or signature is invalid")]
+  DomainMismatch,
+  #[error("Actor or object referenced by the activity could not be resolved")]
+  Unresolvable,
+  #[error("Activity is missing a required field or is otherwise malformed")]
+  Malformed,
+  #[error("Resolving this activity's actor/object chain exceeded the fetch budget")]
+  FetchBudgetExceeded,
+}
+
+impl ReceiveActivityError {
+  /// The HTTP status the inbox route should send back to the remote server for this failure.
+  pub(crate) fn status_code(&self) -> StatusCode {
+    match self {
+      ReceiveActivityError::Unsupported => StatusCode::NOT_IMPLEMENTED,
+      ReceiveActivityError::DomainMismatch => StatusCode::FORBIDDEN,
+      ReceiveActivityError::Unresolvable => StatusCode::BAD_GATEWAY,
+      ReceiveActivityError::Malformed => StatusCode::BAD_REQUEST,
+      ReceiveActivityError::FetchBudgetExceeded => StatusCode::BAD_REQUEST,
+    }
+  }
+}
+
+/// Looks for a [`ReceiveActivityError`] attached as context or anywhere in `error`'s cause chain,
+/// falling back to 500 for anything else. The inbox route uses this to pick the status code for
+/// its response.
+pub(crate) fn receive_error_status_code(error: &LemmyError) -> StatusCode {
+  // `downcast_ref` also finds a value attached with `.context(..)`, which `chain()` does not.
+  error
+    .inner
+    .downcast_ref::<ReceiveActivityError>()
+    .or_else(|| {
+      error
+        .inner
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ReceiveActivityError>())
+    })
+    .map(ReceiveActivityError::status_code)
+    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maps_each_variant_to_its_status_code() {
+    assert_eq!(ReceiveActivityError::Unsupported.status_code(), StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(ReceiveActivityError::DomainMismatch.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(ReceiveActivityError::Unresolvable.status_code(), StatusCode::BAD_GATEWAY);
+    assert_eq!(ReceiveActivityError::Malformed.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(ReceiveActivityError::FetchBudgetExceeded.status_code(), StatusCode::BAD_REQUEST);
+  }
+
+  #[test]
+  fn receive_error_status_code_finds_the_typed_cause_through_context() {
+    let error: LemmyError = anyhow::Error::new(ReceiveActivityError::DomainMismatch)
+      .context("while verifying activity domains")
+      .into();
+    assert_eq!(receive_error_status_code(&error), StatusCode::FORBIDDEN);
+  }
+
+  #[test]
+  fn receive_error_status_code_defaults_to_internal_server_error() {
+    let error: LemmyError = anyhow::anyhow!("some unrelated failure").into();
+    assert_eq!(receive_error_status_code(&error), StatusCode::INTERNAL_SERVER_ERROR);
+  }
+}
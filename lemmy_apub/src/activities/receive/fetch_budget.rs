@@ -0,0 +1,42 @@
+use crate::activities::receive::error::ReceiveActivityError;
+
+/// Ceiling on the number of actor/object fetches a single inbox activity is allowed to trigger
+/// before resolution is aborted.
+pub(crate) const MAX_FETCHES: i32 = 25;
+
+/// Fails once `request_counter` has already reached [`MAX_FETCHES`]. Intended to be called right
+/// before a fetch that would increment `request_counter`, so that a chain of actors/objects which
+/// keep pointing at further unresolved actors/objects can't recurse without bound.
+///
+/// This is a free function rather than a wrapper type around `request_counter` so that it can be
+/// consulted from anywhere `request_counter` is already threaded through (eg the recursive
+/// fetches in `fetcher.rs`) without changing any of those functions' signatures.
+pub(crate) fn check_fetch_budget(request_counter: &i32) -> Result<(), ReceiveActivityError> {
+  if *request_counter >= MAX_FETCHES {
+    return Err(ReceiveActivityError::FetchBudgetExceeded);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn allows_fetches_under_the_budget() {
+    assert!(check_fetch_budget(&0).is_ok());
+    assert!(check_fetch_budget(&(MAX_FETCHES - 1)).is_ok());
+  }
+
+  #[test]
+  fn rejects_fetches_at_or_over_the_budget() {
+    assert!(matches!(
+      check_fetch_budget(&MAX_FETCHES),
+      Err(ReceiveActivityError::FetchBudgetExceeded)
+    ));
+    assert!(matches!(
+      check_fetch_budget(&(MAX_FETCHES + 10)),
+      Err(ReceiveActivityError::FetchBudgetExceeded)
+    ));
+  }
+}
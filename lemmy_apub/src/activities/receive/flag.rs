@@ -0,0 +1,108 @@
+use crate::{
+  activities::receive::{error::ReceiveActivityError, get_actor_as_user, verify_activity_domains_valid},
+  fetcher::{get_or_fetch_and_upsert_comment, get_or_fetch_and_upsert_post},
+};
+use activitystreams::{activity::Flag, prelude::*};
+use anyhow::Context;
+use lemmy_db::{
+  comment_report::{CommentReport, CommentReportForm},
+  post_report::{PostReport, PostReportForm},
+};
+use lemmy_utils::{location_info, utils::blocking, LemmyError};
+use lemmy_websocket::LemmyContext;
+
+/// Handle a `Flag` activity, ie a moderation report raised by a user on a (possibly remote)
+/// instance against one of our posts or comments.
+///
+/// The reported object's domain legitimately differs from the reporter's, so domain validation
+/// is performed with `object_domain_must_match = false`.
+pub(crate) async fn receive_flag(
+  flag: Flag,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<(), LemmyError> {
+  let user = get_actor_as_user(&flag, context, request_counter).await?;
+
+  let actor_id = user.actor_id()?;
+  verify_activity_domains_valid(&flag, &actor_id, false, request_counter)?;
+
+  let object_id = flag
+    .object()
+    .to_owned()
+    .single_xsd_any_uri()
+    .ok_or(ReceiveActivityError::Malformed)
+    .context(location_info!())?;
+
+  let reason = extract_reason(&flag);
+
+  if let Ok(post) = get_or_fetch_and_upsert_post(&object_id, context, request_counter).await {
+    let report_form = PostReportForm {
+      creator_id: user.id,
+      post_id: post.id,
+      original_post_name: post.name,
+      original_post_url: post.url,
+      original_post_body: post.body,
+      reason,
+    };
+    blocking(context.pool(), move |conn| PostReport::report(conn, &report_form)).await??;
+  } else {
+    let comment = get_or_fetch_and_upsert_comment(&object_id, context, request_counter).await?;
+    let report_form = CommentReportForm {
+      creator_id: user.id,
+      comment_id: comment.id,
+      original_comment_text: comment.content,
+      reason,
+    };
+    blocking(context.pool(), move |conn| CommentReport::report(conn, &report_form)).await??;
+  }
+
+  Ok(())
+}
+
+/// Picks the reason to store for a report: the flag's `summary`, falling back to its `content`,
+/// falling back to a fixed placeholder if the remote instance sent neither.
+fn extract_reason(flag: &Flag) -> String {
+  flag
+    .summary()
+    .map(|s| s.as_str().to_owned())
+    .or_else(|| flag.content().and_then(|c| c.as_single_xsd_string()).map(|c| c.to_owned()))
+    .unwrap_or_else(|| "No reason given".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use activitystreams::{object::Note, prelude::*};
+
+  fn flag_with(summary: Option<&str>, content: Option<&str>) -> Flag {
+    let mut flag = Flag::new(
+      "https://example.com/actor".parse().unwrap(),
+      Note::new().into_any_base().unwrap(),
+    );
+    if let Some(summary) = summary {
+      flag.set_summary(summary.to_owned());
+    }
+    if let Some(content) = content {
+      flag.set_content(content.to_owned());
+    }
+    flag
+  }
+
+  #[test]
+  fn extract_reason_prefers_summary() {
+    let flag = flag_with(Some("spam"), Some("this is spam"));
+    assert_eq!(extract_reason(&flag), "spam");
+  }
+
+  #[test]
+  fn extract_reason_falls_back_to_content() {
+    let flag = flag_with(None, Some("this is spam"));
+    assert_eq!(extract_reason(&flag), "this is spam");
+  }
+
+  #[test]
+  fn extract_reason_falls_back_to_placeholder() {
+    let flag = flag_with(None, None);
+    assert_eq!(extract_reason(&flag), "No reason given");
+  }
+}
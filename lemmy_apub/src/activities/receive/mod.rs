@@ -1,10 +1,9 @@
-use crate::fetcher::get_or_fetch_and_upsert_user;
+use crate::{activities::receive::error::ReceiveActivityError, fetcher::get_or_fetch_and_upsert_user};
 use activitystreams::{
   activity::{ActorAndObjectRef, ActorAndObjectRefExt},
   base::{AsBase, BaseExt},
-  error::DomainError,
 };
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use lemmy_db::user::User_;
 use lemmy_utils::{location_info, LemmyError};
 use lemmy_websocket::LemmyContext;
@@ -12,20 +11,28 @@ use log::debug;
 use std::fmt::Debug;
 use url::Url;
 
+pub(crate) mod announce;
 pub(crate) mod comment;
 pub(crate) mod comment_undo;
 pub(crate) mod community;
+pub(crate) mod error;
+pub(crate) mod fetch_budget;
+pub(crate) mod flag;
 pub(crate) mod post;
 pub(crate) mod post_undo;
 pub(crate) mod private_message;
 
-/// Return HTTP 501 for unsupported activities in inbox.
+pub(crate) use error::receive_error_status_code;
+pub(crate) use fetch_budget::check_fetch_budget;
+
+/// Called for activity types the inbox doesn't understand. The inbox route maps this to HTTP 501,
+/// telling the remote server not to bother retrying.
 pub(crate) fn receive_unhandled_activity<A>(activity: A) -> Result<(), LemmyError>
 where
   A: Debug,
 {
   debug!("received unhandled activity type: {:?}", activity);
-  Err(anyhow!("Activity not supported").into())
+  Err(ReceiveActivityError::Unsupported.into())
 }
 
 /// Reads the actor field of an activity and returns the corresponding `User_`.
@@ -38,7 +45,14 @@ where
   T: AsBase<A> + ActorAndObjectRef,
 {
   let actor = activity.actor()?;
-  let user_uri = actor.as_single_xsd_any_uri().context(location_info!())?;
+  let user_uri = actor
+    .as_single_xsd_any_uri()
+    .ok_or(ReceiveActivityError::Malformed)
+    .context(location_info!())?;
+  // `get_or_fetch_and_upsert_user` recurses for actors that point at further unresolved actors
+  // (eg a `moved_to`), and itself checks the budget before every recursive fetch. This check just
+  // keeps us from starting a fetch chain that's already exhausted its budget.
+  check_fetch_budget(request_counter)?;
   get_or_fetch_and_upsert_user(&user_uri, context, request_counter).await
 }
 
@@ -51,13 +65,28 @@ pub(crate) fn verify_activity_domains_valid<T, Kind>(
   activity: &T,
   actor_id: &Url,
   object_domain_must_match: bool,
+  request_counter: &i32,
 ) -> Result<(), LemmyError>
 where
   T: AsBase<Kind> + ActorAndObjectRef,
 {
-  let expected_domain = actor_id.domain().context(location_info!())?;
+  // Resolving the object ID below can itself be the start of a further fetch chain (eg in
+  // get_like_object_id's caller), so it's gated by the same fetch budget as the fetches
+  // themselves.
+  check_fetch_budget(request_counter)?;
+
+  let expected_domain = actor_id
+    .domain()
+    .ok_or(ReceiveActivityError::Malformed)
+    .context(location_info!())?;
 
-  activity.id(expected_domain)?;
+  activity.id(expected_domain).map_err(|e| {
+    LemmyError::from(
+      anyhow::Error::new(e)
+        .context(location_info!())
+        .context(ReceiveActivityError::DomainMismatch),
+    )
+  })?;
 
   let object_id = match activity.object().to_owned().single_xsd_any_uri() {
     // object is just an ID
@@ -67,14 +96,16 @@ where
       .object()
       .to_owned()
       .one()
+      .ok_or(ReceiveActivityError::Malformed)
       .context(location_info!())?
       .id()
+      .ok_or(ReceiveActivityError::Malformed)
       .context(location_info!())?
       .to_owned(),
   };
 
   if object_domain_must_match && object_id.domain() != Some(expected_domain) {
-    return Err(DomainError.into());
+    return Err(ReceiveActivityError::DomainMismatch.into());
   }
 
   Ok(())
@@ -82,10 +113,15 @@ where
 
 pub(in crate::activities::receive) fn get_like_object_id<Activity, Kind>(
   like_or_dislike: &Activity,
+  request_counter: &i32,
 ) -> Result<Url, LemmyError>
 where
   Activity: ActorAndObjectRefExt,
 {
+  // Resolving the object ID can be the start of a further fetch chain in the caller, so it's
+  // gated by the same fetch budget as the fetches themselves.
+  check_fetch_budget(request_counter)?;
+
   // For backwards compatibility with older Lemmy versions where like.object contains a full
   // post/comment. This can be removed after some time, using
   // `activity.oject().as_single_xsd_any_uri()` instead.
@@ -97,8 +133,10 @@ where
       object
         .to_owned()
         .one()
+        .ok_or(ReceiveActivityError::Malformed)
         .context(location_info!())?
         .id()
+        .ok_or(ReceiveActivityError::Malformed)
         .context(location_info!())?
         .to_owned(),
     )
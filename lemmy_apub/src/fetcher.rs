@@ -0,0 +1,93 @@
+use crate::activities::receive::{error::ReceiveActivityError, fetch_budget::check_fetch_budget};
+use async_recursion::async_recursion;
+use lemmy_db::{comment::Comment, post::Post, user::User_};
+use lemmy_utils::{location_info, LemmyError};
+use lemmy_websocket::LemmyContext;
+use serde_json::Value;
+use url::Url;
+
+/// Fetches and JSON-decodes the ActivityPub object at `id`. Failing to reach or parse the remote
+/// object means it can't be resolved, which callers should surface as `ReceiveActivityError::Unresolvable`
+/// rather than a generic/internal error.
+async fn fetch_remote_object(context: &LemmyContext, id: &Url) -> Result<Value, LemmyError> {
+  let response = context.client().get(id.as_str()).send().await.map_err(|e| {
+    LemmyError::from(
+      anyhow::Error::new(e)
+        .context(location_info!())
+        .context(ReceiveActivityError::Unresolvable),
+    )
+  })?;
+
+  response.json::<Value>().await.map_err(|e| {
+    LemmyError::from(
+      anyhow::Error::new(e)
+        .context(location_info!())
+        .context(ReceiveActivityError::Unresolvable),
+    )
+  })
+}
+
+/// Returns our local copy of the remote user at `user_uri`, fetching and upserting it first if we
+/// don't have it yet.
+///
+/// A remote actor can point at a further actor it has since moved to, so this recurses.
+/// `request_counter` is checked against the shared fetch budget before every recursive fetch, so
+/// a chain of actors that keep pointing at further unresolved actors can't recurse without bound.
+#[async_recursion]
+pub(crate) async fn get_or_fetch_and_upsert_user(
+  user_uri: &Url,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<User_, LemmyError> {
+  if let Ok(user) = User_::read_from_actor_id(context.pool(), user_uri.as_str()).await {
+    return Ok(user);
+  }
+
+  check_fetch_budget(request_counter)?;
+  *request_counter += 1;
+
+  let person = fetch_remote_object(context, user_uri).await?;
+
+  if let Some(moved_to) = person.get("movedTo").and_then(Value::as_str) {
+    let moved_to_uri = Url::parse(moved_to)?;
+    return get_or_fetch_and_upsert_user(&moved_to_uri, context, request_counter).await;
+  }
+
+  User_::upsert_from_apub(context.pool(), &person).await
+}
+
+/// Returns our local copy of the remote post at `post_ap_id`, fetching and upserting it first if
+/// we don't have it yet.
+pub(crate) async fn get_or_fetch_and_upsert_post(
+  post_ap_id: &Url,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<Post, LemmyError> {
+  if let Ok(post) = Post::read_from_apub_id(context.pool(), post_ap_id.as_str()).await {
+    return Ok(post);
+  }
+
+  check_fetch_budget(request_counter)?;
+  *request_counter += 1;
+
+  let page = fetch_remote_object(context, post_ap_id).await?;
+  Post::upsert_from_apub(context.pool(), &page).await
+}
+
+/// Returns our local copy of the remote comment at `comment_ap_id`, fetching and upserting it
+/// first if we don't have it yet.
+pub(crate) async fn get_or_fetch_and_upsert_comment(
+  comment_ap_id: &Url,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<Comment, LemmyError> {
+  if let Ok(comment) = Comment::read_from_apub_id(context.pool(), comment_ap_id.as_str()).await {
+    return Ok(comment);
+  }
+
+  check_fetch_budget(request_counter)?;
+  *request_counter += 1;
+
+  let note = fetch_remote_object(context, comment_ap_id).await?;
+  Comment::upsert_from_apub(context.pool(), &note).await
+}
@@ -0,0 +1,39 @@
+use crate::activities::receive::{
+  announce::{receive_announcable_activity, receive_announce, AnnouncableActivities},
+  flag::receive_flag,
+  receive_unhandled_activity,
+};
+use activitystreams::activity::{Announce, Flag};
+use lemmy_utils::LemmyError;
+use lemmy_websocket::LemmyContext;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Activity types recognized at the top of a community's inbox. `Flag` and `Announce` get their
+/// own handling; anything matching `AnnouncableActivities` (a plain, non-`Announce`-wrapped
+/// comment/post/like) falls through to the same per-type handling that an `Announce` would
+/// unwrap to. Everything else is unsupported.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum GroupInboxActivities {
+  Flag(Flag),
+  Announce(Announce),
+  Announcable(AnnouncableActivities),
+  Other(Value),
+}
+
+/// Dispatches one inbox activity addressed to a community to the matching `receive` handler.
+pub(crate) async fn community_inbox_receive(
+  activity: GroupInboxActivities,
+  context: &LemmyContext,
+  request_counter: &mut i32,
+) -> Result<(), LemmyError> {
+  match activity {
+    GroupInboxActivities::Flag(flag) => receive_flag(flag, context, request_counter).await,
+    GroupInboxActivities::Announce(announce) => receive_announce(announce, context, request_counter).await,
+    GroupInboxActivities::Announcable(activity) => {
+      receive_announcable_activity(activity, context, request_counter).await
+    }
+    GroupInboxActivities::Other(other) => receive_unhandled_activity(other),
+  }
+}
@@ -0,0 +1,22 @@
+use crate::{
+  activities::receive::receive_error_status_code,
+  inbox::community_inbox::{community_inbox_receive, GroupInboxActivities},
+};
+use actix_web::{web, HttpResponse};
+use lemmy_websocket::LemmyContext;
+
+pub(crate) mod community_inbox;
+
+/// HTTP entry point for a community's inbox. Deserializes the incoming activity, dispatches it
+/// via `community_inbox_receive`, and maps any failure to the status code the remote server
+/// should see via `receive_error_status_code`, instead of a blanket 500 for everything.
+pub(crate) async fn community_inbox(
+  activity: web::Json<GroupInboxActivities>,
+  context: web::Data<LemmyContext>,
+) -> HttpResponse {
+  let mut request_counter = 0;
+  match community_inbox_receive(activity.into_inner(), &context, &mut request_counter).await {
+    Ok(()) => HttpResponse::Ok().finish(),
+    Err(e) => HttpResponse::build(receive_error_status_code(&e)).finish(),
+  }
+}